@@ -1,4 +1,68 @@
 use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+type Shares = (Vec<i32>,Vec<i32>); // (xi, yi)
+
+// all arithmetic is i32, a*b and x0 + p*x1 in inv_modp need prime^2 - 1 <= i32::MAX
+const MAX_PRIME:i32 = 46340;
+
+#[allow(dead_code)] // fields only read via Debug
+#[derive(Debug)]
+enum Error {
+    PointCollisionModPrime(i32,i32), // xi == xj mod prime, den would be 0
+    ShareValueOutOfRange(i32), // y not in [0,prime), wrong field or corrupted
+    WeakCoefficients, // poly looks like a broken rng made it
+    LengthMismatch(usize,usize), // #xs != #ys
+    SharesExceedFieldSize(i32), // only prime-1 nonzero x exist
+    DenominatorDivisibleByPrime(i32), // den%prime == 0, colliding points
+    NotCoprime(i32), // gcd(den,modulus) != 1 w/o den%modulus == 0, modulus isn't prime
+    DuplicatePoint(i32), // same x passed twice
+    TooFewShares(i32,usize), // (no_of_shares, threshold), m unrecoverable
+    InvalidPoint(i32), // x == 0 mod prime, its share is m itself
+    NoShares, // nothing to interpolate, not a secret of 0
+    PointMismatch(i32,i32), // two share sets differ in x at the same position
+    PrimeTooLarge(i32), // > MAX_PRIME, products would overflow i32
+}
+
+#[allow(dead_code)]
+impl Error {
+    // stable codes for ffi, append new variants, never renumber or reuse
+    fn code(&self) -> u32{
+        match self {
+            Error::PointCollisionModPrime(..) => 1,
+            Error::ShareValueOutOfRange(..) => 2,
+            Error::WeakCoefficients => 3,
+            Error::LengthMismatch(..) => 4,
+            Error::SharesExceedFieldSize(..) => 5,
+            Error::DenominatorDivisibleByPrime(..) => 6,
+            Error::NotCoprime(..) => 7,
+            Error::DuplicatePoint(..) => 8,
+            Error::TooFewShares(..) => 9,
+            Error::InvalidPoint(..) => 10,
+            Error::NoShares => 11,
+            Error::PointMismatch(..) => 12,
+            Error::PrimeTooLarge(..) => 13,
+        }
+    }
+    fn variant_name(&self) -> &'static str{
+        match self {
+            Error::PointCollisionModPrime(..) => "PointCollisionModPrime",
+            Error::ShareValueOutOfRange(..) => "ShareValueOutOfRange",
+            Error::WeakCoefficients => "WeakCoefficients",
+            Error::LengthMismatch(..) => "LengthMismatch",
+            Error::SharesExceedFieldSize(..) => "SharesExceedFieldSize",
+            Error::DenominatorDivisibleByPrime(..) => "DenominatorDivisibleByPrime",
+            Error::NotCoprime(..) => "NotCoprime",
+            Error::DuplicatePoint(..) => "DuplicatePoint",
+            Error::TooFewShares(..) => "TooFewShares",
+            Error::InvalidPoint(..) => "InvalidPoint",
+            Error::NoShares => "NoShares",
+            Error::PointMismatch(..) => "PointMismatch",
+            Error::PrimeTooLarge(..) => "PrimeTooLarge",
+        }
+    }
+}
+
 /*
 
 #[allow(dead_code)]
@@ -10,24 +74,56 @@ fn inverse(){
 
 }
  */
-fn gen_ran_in_fp(prime:i32)-> i32{
-    let mut rng = rand::thread_rng(); //why thread_rng
-    let y:i32 = rng.gen();
-    (prime+y%prime)%(prime)
+fn gen_ran_in_fp(prime:i32, rng:&mut (impl Rng + CryptoRng))-> i32{
+    // caller picks the rng, seeded one in tests, thread_rng otherwise
+    // CryptoRng since the coefficients are what hide m
+    // gen_range rejects out of range draws so no y%prime bias
+    rng.gen_range(0..prime)
 }
 
-fn gen_poly(m:i32, prime:i32 , degree:u8) -> Vec<i32>{
-    let mut vec:Vec<i32> = Vec::with_capacity((degree+1) as usize);
-    vec.push(m);
-    for _ in 0..(degree) {
+fn gen_poly(m:i32, prime:i32 , degree:u8, rng:&mut (impl Rng + CryptoRng)) -> Vec<i32>{
+    let mut vec:Vec<i32> = Vec::with_capacity(degree as usize + 1); // degree+1 in u8 overflows at 255
+    // m is stored mod prime, so reconstruct gives back m%prime
+    // which differs from m if m >= prime (or m < 0). pick prime > m
+    vec.push((prime+m%prime)%prime);
+    for _ in 0..degree.saturating_sub(1) { // no underflow when degree is 0
         //let tmp = gen_ran_in_fp(prime);
-        vec.push(gen_ran_in_fp(prime));
+        vec.push(gen_ran_in_fp(prime, rng));
     } 
+    if degree > 0 { // leading coeff must be nonzero, else poly drops a degree
+        let mut lead:i32 = gen_ran_in_fp(prime, rng);
+        while lead == 0 {
+            lead = gen_ran_in_fp(prime, rng);
+        }
+        vec.push(lead);
+    }
     vec
 }
 
+#[allow(dead_code)]
+fn audit_coefficients(prime:i32, pn:&[i32]) -> Result<(),Error>{
+    // heuristic only, catches stubbed/constant rngs, not subtle bias
+    if pn.is_empty() { // not even m, whatever made this is broken
+        return Err(Error::WeakCoefficients);
+    }
+    let c = &pn[1..]; // skip m
+    if c.last() == Some(&0) {
+        return Err(Error::WeakCoefficients);
+    }
+    // patterns need >=3 coeffs, else they happen by chance too often in small fields
+    if c.len() >= 3 {
+        let same = c.windows(2).all(|w| w[0] == w[1]);
+        let seq = c.windows(2).all(|w| (w[0] + 1) % prime == w[1]);
+        if same || seq {
+            return Err(Error::WeakCoefficients);
+        }
+    }
+    Ok(())
+}
+
 #[allow(dead_code)]
 fn eval_px_at_xi(prime:i32, pn:&Vec<i32>, x:i32)-> i32{
+    // needs prime <= MAX_PRIME, gen_share checks it before calling
     let mut y:i32 = (*pn)[0];
     let mut x_pow:i32 = x;
 
@@ -42,7 +138,16 @@ fn eval_px_at_xi(prime:i32, pn:&Vec<i32>, x:i32)-> i32{
 }   
 
 #[allow(dead_code)]
-fn gen_share(prime:i32, pn:&Vec<i32>, no_of_shares:i32) -> (Vec<i32>,Vec<i32>){
+fn gen_share(prime:i32, pn:&Vec<i32>, no_of_shares:i32, rng:&mut (impl Rng + CryptoRng)) -> Result<Shares,Error>{
+    if prime > MAX_PRIME {
+        return Err(Error::PrimeTooLarge(prime));
+    }
+    if no_of_shares > prime - 1 { // e.g. 30 shares over 23, the x loop below never ends
+        return Err(Error::SharesExceedFieldSize(no_of_shares));
+    }
+    if no_of_shares < pn.len() as i32 { // threshold is degree+1 = pn.len()
+        return Err(Error::TooFewShares(no_of_shares, pn.len()));
+    }
     let mut yi:Vec<i32> = Vec::with_capacity(no_of_shares as usize);
     let mut xi:Vec<i32> = Vec::with_capacity(no_of_shares as usize);
 
@@ -50,15 +155,110 @@ fn gen_share(prime:i32, pn:&Vec<i32>, no_of_shares:i32) -> (Vec<i32>,Vec<i32>){
     for i in 0..(no_of_shares as usize) {
         /* need to check for unique x's
         */
-        let mut tmp:i32 = gen_ran_in_fp(prime);
+        let mut tmp:i32 = gen_ran_in_fp(prime, rng);
         while tmp == 0 || xi.contains(&tmp) { // linear op on all Fp
-            tmp = gen_ran_in_fp(prime);
+            tmp = gen_ran_in_fp(prime, rng);
         } 
         xi.push(tmp);
-        yi.push(eval_px_at_xi(prime, &pn, xi[i]));
+        yi.push(eval_px_at_xi(prime, pn, xi[i]));
+    }
+
+    Ok((xi,yi))
+}
+
+#[allow(dead_code)]
+fn deal_seeded(m:i32, prime:i32, degree:u8, no_of_shares:i32, seed:[u8;32]) -> Result<(Vec<i32>,Shares),Error>{
+    // for golden test vectors ONLY, anyone with the seed can rebuild pn and so m
+    // same seed + params give the same poly and shares for a fixed rand/rand_chacha
+    let mut rng = ChaCha20Rng::from_seed(seed);
+    let pn = gen_poly(m, prime, degree, &mut rng);
+    let shares = gen_share(prime, &pn, no_of_shares, &mut rng)?;
+    Ok((pn,shares))
+}
+
+#[allow(dead_code)]
+fn verify_share(prime:i32, pn:&Vec<i32>, x:i32, y:i32) -> bool{
+    // dealer side check, needs the poly. catches corrupted/tampered shares
+    eval_px_at_xi(prime, pn, x) == y
+}
+
+#[allow(dead_code)]
+fn verification_point(prime:i32, pn:&Vec<i32>, x:i32) -> Result<(i32,i32),Error>{
+    // public checkpoint (x, P(x)) the dealer signs, x must not be 0 or it's m
+    if prime > MAX_PRIME {
+        return Err(Error::PrimeTooLarge(prime));
+    }
+    let x:i32 = (prime+x%prime)%prime;
+    if x == 0 {
+        return Err(Error::InvalidPoint(x));
+    }
+    Ok((x, eval_px_at_xi(prime, pn, x)))
+}
+
+#[allow(dead_code)]
+fn verify_against_checkpoint(prime:i32, shares:&Shares, checkpoint:&(i32,i32)) -> bool{
+    // reconstructor side, the interpolated poly must pass through the checkpoint
+    // needs >= threshold shares, and any bad share set (errors) fails too
+    match interpolate_at(shares, prime, checkpoint.0) {
+        Ok((y, _)) => y == (prime+checkpoint.1%prime)%prime,
+        Err(_) => false,
+    }
+}
+
+#[allow(dead_code)]
+fn scale_shares(prime:i32, shares:&Shares, k:i32) -> Result<Shares,Error>{
+    // y*k on every share gives shares of k*m, x's stay the same
+    check_shares(shares, prime)?; // % below would pull a bad y back in range
+    let k = (prime+k%prime)%prime;
+    let yi:Vec<i32> = shares.1.iter().map(|&y| (y*k)%prime).collect();
+    Ok((shares.0.clone(), yi))
+}
+
+#[allow(dead_code)]
+fn add_shares(prime:i32, a:&Shares, b:&Shares) -> Result<Shares,Error>{
+    // shares of A + shares of B at the same x's give shares of A+B
+    check_shares(a, prime)?;
+    check_shares(b, prime)?;
+    if a.0.len() != b.0.len() {
+        return Err(Error::LengthMismatch(a.0.len(), b.0.len()));
     }
+    for (&xa, &xb) in a.0.iter().zip(b.0.iter()) {
+        if xa != xb {
+            return Err(Error::PointMismatch(xa, xb));
+        }
+    }
+    let yi:Vec<i32> = a.1.iter().zip(b.1.iter()).map(|(&ya, &yb)| (ya + yb)%prime).collect();
+    Ok((a.0.clone(), yi))
+}
 
-    (xi,yi)
+#[allow(dead_code)]
+fn mask_shares(prime:i32, shares:&Shares, pad:i32) -> Result<Shares,Error>{
+    // one time pad on y, send masked and hand out pad separately
+    check_shares(shares, prime)?; // % below would pull a bad y back in range
+    let pad = (prime+pad%prime)%prime;
+    let yi:Vec<i32> = shares.1.iter().map(|&y| (y + pad)%prime).collect();
+    Ok((shares.0.clone(), yi))
+}
+
+#[allow(dead_code)]
+fn unmask_shares(prime:i32, shares:&Shares, pad:i32) -> Result<Shares,Error>{
+    check_shares(shares, prime)?;
+    let pad = (prime+pad%prime)%prime;
+    // + prime so y < pad doesn't go -ve
+    let yi:Vec<i32> = shares.1.iter().map(|&y| (y + prime - pad)%prime).collect();
+    Ok((shares.0.clone(), yi))
+}
+
+#[allow(dead_code)]
+fn refresh_shares(prime:i32, shares:&Shares, degree:u8, rng:&mut (impl Rng + CryptoRng)) -> Result<Shares,Error>{
+    // add evals of a fresh poly w m=0, same m but old and new shares don't mix
+    // degree must be the original one, else the threshold changes
+    check_shares(shares, prime)?; // zip below would silently drop unmatched ys
+    let zero_pn:Vec<i32> = gen_poly(0, prime, degree, rng);
+    let yi:Vec<i32> = shares.0.iter().zip(shares.1.iter())
+        .map(|(&x, &y)| (y + eval_px_at_xi(prime, &zero_pn, (prime+x%prime)%prime))%prime)
+        .collect();
+    Ok((shares.0.clone(), yi))
 }
 
 fn gcd(x:i32,y:i32) -> i32{
@@ -68,13 +268,15 @@ fn gcd(x:i32,y:i32) -> i32{
         gcd(y,x%y)
     }
 }
-fn inv_modp(a: i32,prime:i32) -> i32{ //change a to take -ve valeus, else sanitize inputs before
-    if gcd(a,prime) != 1 {
-        println!("Not coprime. Can't find inverse.");
-        0
+fn inv_modp(a: i32,prime:i32) -> Result<i32,Error>{
+    // reduce first, gcd of a -ve a can be -1 and look non coprime
+    let red:i32 = (prime+a%prime)%prime;
+    if red == 0 {
+        Err(Error::DenominatorDivisibleByPrime(a))
+    }else if gcd(red,prime) != 1 {
+        Err(Error::NotCoprime(a))
     }else { //without else it doesn't work
-        let a = (prime+a)%prime; //defined antoher a
-        let mut r:[i32;3] = [a,prime,0];
+        let mut r:[i32;3] = [red,prime,0];
         let mut x:[i32;3] = [1,0,0];
         let mut y:[i32;3] = [0,1,0];
         //let mut q:i32=0;
@@ -90,60 +292,489 @@ fn inv_modp(a: i32,prime:i32) -> i32{ //change a to take -ve valeus, else saniti
             y[0] = y[1]; y[1] = y[2];
 
         }
-        x[1]
+        Ok(x[1])
+    }
+}
+
+fn check_shares(shares:&Shares, prime:i32) -> Result<(),Error>{
+    if prime > MAX_PRIME {
+        return Err(Error::PrimeTooLarge(prime));
+    }
+    if shares.1.len() != shares.0.len() { // else indexing ys panics or extra ys are ignored
+        return Err(Error::LengthMismatch(shares.0.len(), shares.1.len()));
+    }
+    for &y in shares.1.iter() { // reject rather than reduce, a big y means wrong shares
+        if !(0..prime).contains(&y) {
+            return Err(Error::ShareValueOutOfRange(y));
+        }
     }
+    Ok(())
 }
 
 #[allow(dead_code)]
-fn reconstruct(shares:&(Vec<i32>,Vec<i32>),prime:i32) -> i32{
+fn reconstruct(shares:&(Vec<i32>,Vec<i32>),prime:i32) -> Result<i32,Error>{
     /* Steps
         1. Lagrange interpolation
         2. Return m, the constant coefficient
      */
+    interpolate_at(shares, prime, 0).map(|(m, _)| m)
+}
+
+#[allow(dead_code)]
+fn reconstruct_verbose(shares:&Shares, prime:i32) -> Result<(i32,Vec<i32>),Error>{
+    // m plus each share's lagrange weight li(0), m = sum yi*li mod p
+    // the weights depend only on the x's, e.g. for threshold signatures
+    interpolate_at(shares, prime, 0)
+}
+
+#[allow(dead_code)]
+fn issue_share_for(shares:&Shares, prime:i32, new_x:i32) -> Result<(i32,i32),Error>{
+    // new party gets P(new_x) from >= threshold existing shares, m is never rebuilt
+    // caller must pass enough shares, with fewer the value is off the poly
+    if new_x % prime == 0 { // P(0) is m
+        return Err(Error::InvalidPoint(new_x));
+    }
+    if shares.0.iter().any(|&xj| (xj - new_x) % prime == 0) {
+        return Err(Error::DuplicatePoint(new_x));
+    }
+    // hand back x in [0,prime) so eval_px_at_xi/verify_share agree with it
+    let x:i32 = (prime+new_x%prime)%prime;
+    Ok((x, interpolate_at(shares, prime, x)?.0))
+}
+
+fn interpolate_at(shares:&Shares, prime:i32, at:i32) -> Result<(i32,Vec<i32>),Error>{
+    // P(at) from the points, Lagrange basis evaluated at `at` instead of 0
+    // also returns the basis values li(at), one per share
+    let at = (prime+at%prime)%prime;
     let n = shares.0.len();
     let mut res:i32 = 0;
+    let mut weights:Vec<i32> = Vec::with_capacity(n);
 
-    for i in 0..n {
+    if n == 0 { // else Ok(0), a valid looking wrong secret
+        return Err(Error::NoShares);
+    }
+    check_shares(shares, prime)?;
+    for (i, xj) in shares.0.iter().enumerate() { // e.g. same share submitted twice
+        if shares.0[..i].contains(xj) {
+            return Err(Error::DuplicatePoint(*xj));
+        }
+    }
+
+    // xs reduced into [0,prime) so -ve or big x work, -xj mod p computed once per j
+    let xs:Vec<i32> = shares.0.iter().map(|&xj| (prime + xj%prime)%prime).collect();
+    let neg_x:Vec<i32> = xs.iter().map(|&xj| (prime - xj)%prime).collect();
+
+    for (i, &xi) in xs.iter().enumerate() {
         let mut num:i32=1;
         let mut den:i32=1;
-        for j in 0..n {
+        for (j, &nx) in neg_x.iter().enumerate() {
             if i == j {continue;}
             else {
-                let tmpj:i32 = prime-shares.0[j];  
-                num = (num * (tmpj)) % prime;
-                den = (den * ((shares.0[i] + tmpj) % prime)) % prime; 
+                let diff:i32 = (xi + nx) % prime;
+                if diff == 0 { // e.g. x=2 and x=19 over 17, inv_modp(0) is garbage
+                    return Err(Error::PointCollisionModPrime(shares.0[i], shares.0[j]));
+                }
+                num = (num * ((at + nx) % prime)) % prime;
+                den = (den * diff) % prime; 
             }
         }
-        res = (res + (shares.1[i] * (num * inv_modp(den, prime))%prime)%prime)%prime;
+        let li:i32 = (num * inv_modp(den, prime)?) % prime;
+        let term:i32 = (shares.1[i] * li) % prime; // one reduction, products stay < prime^2
+        res = (res + term) % prime;
+        weights.push(li);
     }
 
-    res
+    Ok((res, weights))
 }
 
 fn main() {
     let prime:i32 = 17; // starting  w prime field, later generalize if possible
     let m:i32 = 6;
     let degree = 7;
-    let pn:Vec<i32> = gen_poly(m, prime, degree);
+    let mut rng = rand::thread_rng();
+    let pn:Vec<i32> = gen_poly(m, prime, degree, &mut rng);
     
     print!("{} ",pn[0]);
-    for i in 1..=(degree as usize) { 
-        print!("+ {}x^{}",pn[i],i);
+    for (i, c) in pn.iter().enumerate().skip(1) { 
+        print!("+ {}x^{}",c,i);
     }
     println!("\nxi -> yi");
     
     let no_of_shares:i32=9; // must be >degree, 
     // on trying w <=degree shares, v get random outputs
     // w > degree, we get m everytime
-    let shares:(Vec<i32>, Vec<i32>) = gen_share(prime, &pn, no_of_shares);
+    let shares:(Vec<i32>, Vec<i32>) = match gen_share(prime, &pn, no_of_shares, &mut rng) {
+        Ok(s) => s,
+        Err(e) => { println!("{:?}",e); return; }
+    };
     for i in 0..no_of_shares {
         println!("{} -> {}",shares.0[i as usize],shares.1[i as usize])
     }
     /*
      */
     //println!("{} ",inv_modp(4, 11));
-    let mdash = reconstruct(&shares, prime);
-    println!("{}",mdash);
+    match reconstruct(&shares, prime) {
+        Ok(mdash) => println!("{}",mdash),
+        Err(e) => println!("{:?}",e),
+    }
     
     
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIME:i32 = 97;
+
+    fn seeded(b:u8) -> ChaCha20Rng{
+        ChaCha20Rng::from_seed([b;32])
+    }
+
+    // m = 42 at threshold 4 over PRIME, n shares
+    fn dealt(n:i32) -> (Vec<i32>,Shares){
+        deal_seeded(42, PRIME, 3, n, [5;32]).unwrap()
+    }
+
+    #[test]
+    fn threshold_two_is_a_line() {
+        let prime = 17;
+        let mut rng = seeded(1);
+        let pn = gen_poly(6, prime, 1, &mut rng);
+        assert_eq!(pn.len(), 2);
+        assert_eq!(pn[0], 6);
+        assert_ne!(pn[1], 0);
+
+        let shares = gen_share(prime, &pn, 2, &mut rng).unwrap();
+        assert_eq!(shares.0.len(), 2);
+        assert_eq!(reconstruct(&shares, prime).unwrap(), 6);
+    }
+
+    #[test]
+    fn threshold_two_one_share_is_not_enough() {
+        let prime = 17;
+        let mut rng = seeded(2);
+        let pn = gen_poly(6, prime, 1, &mut rng);
+        assert!(matches!(gen_share(prime, &pn, 1, &mut rng), Err(Error::TooFewShares(1, 2))));
+    }
+
+    #[test]
+    fn gen_poly_degree_one_has_nonzero_lead() {
+        for b in 0..32 {
+            let pn = gen_poly(6, 17, 1, &mut seeded(b));
+            assert_eq!(pn.len(), 2);
+            assert_ne!(pn[1], 0);
+        }
+    }
+
+    #[test]
+    fn gen_poly_degree_zero_is_just_m() {
+        assert_eq!(gen_poly(6, 17, 0, &mut seeded(3)), vec![6]);
+        assert_eq!(gen_poly(23, 17, 0, &mut seeded(3)), vec![6]); // stored mod prime
+    }
+
+    #[test]
+    fn any_threshold_subset_gives_same_secret() {
+        let (pn, shares) = dealt(10);
+        let t = pn.len();
+        let mut rng = seeded(6);
+        let idx:Vec<usize> = (0..shares.0.len()).collect();
+        for _ in 0..25 {
+            let pick:Vec<usize> = idx.choose_multiple(&mut rng, t).cloned().collect();
+            let sub:Shares = (pick.iter().map(|&i| shares.0[i]).collect(),
+                              pick.iter().map(|&i| shares.1[i]).collect());
+            assert_eq!(reconstruct(&sub, PRIME).unwrap(), 42);
+        }
+    }
+
+    #[test]
+    fn negative_x_is_reduced_not_rejected() {
+        // -20 = 14 mod 17, used to come back as NotCoprime(-6)
+        assert_eq!(reconstruct(&(vec![-20,3],vec![1,1]),17).unwrap(), 1);
+        assert_eq!(reconstruct(&(vec![-20,5],vec![1,1]),17).unwrap(), 1);
+        assert_eq!(inv_modp(-6, 17).unwrap(), inv_modp(11, 17).unwrap());
+    }
+
+    #[test]
+    fn inv_modp_errors() {
+        assert!(matches!(inv_modp(0, 17), Err(Error::DenominatorDivisibleByPrime(0))));
+        assert!(matches!(inv_modp(-34, 17), Err(Error::DenominatorDivisibleByPrime(-34))));
+        // only a composite modulus can give NotCoprime
+        assert!(matches!(inv_modp(6, 15), Err(Error::NotCoprime(6))));
+        assert!(matches!(inv_modp(-6, 15), Err(Error::NotCoprime(-6))));
+        assert!(matches!(reconstruct(&(vec![1,4],vec![1,1]),15), Err(Error::NotCoprime(..))));
+    }
+
+    #[test]
+    fn empty_share_set_is_an_error() {
+        assert!(matches!(reconstruct(&(vec![],vec![]),17), Err(Error::NoShares)));
+    }
+
+    #[test]
+    fn points_colliding_mod_prime_are_reported() {
+        // 19 = 2 mod 17
+        assert!(matches!(reconstruct(&(vec![2,19],vec![1,1]),17),
+            Err(Error::PointCollisionModPrime(2,19))));
+        assert!(matches!(reconstruct(&(vec![19,5,2],vec![1,1,1]),17),
+            Err(Error::PointCollisionModPrime(19,2))));
+    }
+
+    #[test]
+    fn verify_share_catches_a_flipped_bit() {
+        let (pn, shares) = dealt(5);
+        let (x, y) = (shares.0[0], shares.1[0]);
+        assert!(verify_share(PRIME, &pn, x, y));
+        for bit in 0..7 {
+            assert!(!verify_share(PRIME, &pn, x, y ^ (1 << bit)));
+        }
+    }
+
+    #[test]
+    fn degree_255_boundary() {
+        // degree+1 used to overflow u8 here
+        let prime = 257;
+        let (pn, shares) = deal_seeded(6, prime, 255, 256, [4;32]).unwrap();
+        assert_eq!(pn.len(), 256);
+        assert_ne!(pn[255], 0);
+        assert_eq!(shares.0.len(), 256);
+        assert_eq!(reconstruct(&shares, prime).unwrap(), 6);
+    }
+
+    fn every_error() -> Vec<Error>{
+        // the match is a compile time reminder, a new variant must be listed here too
+        fn listed(e:&Error) {
+            match e {
+                Error::PointCollisionModPrime(..) | Error::ShareValueOutOfRange(..)
+                | Error::WeakCoefficients | Error::LengthMismatch(..)
+                | Error::SharesExceedFieldSize(..) | Error::DenominatorDivisibleByPrime(..)
+                | Error::NotCoprime(..) | Error::DuplicatePoint(..) | Error::TooFewShares(..)
+                | Error::InvalidPoint(..) | Error::NoShares | Error::PointMismatch(..)
+                | Error::PrimeTooLarge(..) => {}
+            }
+        }
+        let all = vec![
+            Error::PointCollisionModPrime(0,0), Error::ShareValueOutOfRange(0),
+            Error::WeakCoefficients, Error::LengthMismatch(0,0),
+            Error::SharesExceedFieldSize(0), Error::DenominatorDivisibleByPrime(0),
+            Error::NotCoprime(0), Error::DuplicatePoint(0), Error::TooFewShares(0,0),
+            Error::InvalidPoint(0), Error::NoShares, Error::PointMismatch(0,0),
+            Error::PrimeTooLarge(0),
+        ];
+        all.iter().for_each(listed);
+        all
+    }
+
+    #[test]
+    fn error_codes_and_names_are_unique() {
+        let all = every_error();
+        let mut codes:Vec<u32> = all.iter().map(|e| e.code()).collect();
+        let mut names:Vec<&str> = all.iter().map(|e| e.variant_name()).collect();
+        codes.sort();
+        codes.dedup();
+        names.sort();
+        names.dedup();
+        assert_eq!(codes.len(), all.len());
+        assert_eq!(names.len(), all.len());
+        for e in &all {
+            assert_eq!(e.variant_name(), format!("{:?}", e).split('(').next().unwrap());
+        }
+    }
+
+    #[test]
+    fn deal_seeded_golden_vector() {
+        // pinned output, a rand/rand_chacha bump that changes it must be noticed
+        let (pn, shares) = deal_seeded(6, 17, 3, 5, [7;32]).unwrap();
+        assert_eq!(pn, vec![6, 8, 4, 12]);
+        assert_eq!(shares, (vec![7, 15, 2, 6, 4], vec![5, 12, 15, 2, 3]));
+        assert_eq!(deal_seeded(6, 17, 3, 5, [7;32]).unwrap(), (pn, shares));
+    }
+
+    #[test]
+    fn gen_poly_with_seeded_rng_is_exact() {
+        assert_eq!(gen_poly(6, 97, 4, &mut seeded(11)), vec![6, 77, 45, 4, 57]);
+        assert_eq!(gen_poly(6, 97, 4, &mut seeded(11)), gen_poly(6, 97, 4, &mut seeded(11)));
+        assert_eq!(gen_poly(6, 97, 4, &mut seeded(12)), vec![6, 10, 28, 34, 33]);
+    }
+
+    #[test]
+    fn issue_share_for_matches_the_polynomial() {
+        let (pn, shares) = dealt(4);
+        for new_x in 1..PRIME {
+            if shares.0.contains(&new_x) { continue; }
+            let (x, y) = issue_share_for(&shares, PRIME, new_x).unwrap();
+            assert_eq!(x, new_x);
+            assert_eq!(y, eval_px_at_xi(PRIME, &pn, new_x));
+        }
+    }
+
+    #[test]
+    fn issue_share_for_rejects_zero_and_taken_points() {
+        let (_, shares) = dealt(4);
+        assert!(matches!(issue_share_for(&shares, PRIME, 0), Err(Error::InvalidPoint(0))));
+        assert!(matches!(issue_share_for(&shares, PRIME, 2*PRIME), Err(Error::InvalidPoint(..))));
+        let taken = shares.0[1];
+        assert!(matches!(issue_share_for(&shares, PRIME, taken), Err(Error::DuplicatePoint(..))));
+        assert!(matches!(issue_share_for(&shares, PRIME, taken + PRIME), Err(Error::DuplicatePoint(..))));
+    }
+
+    #[test]
+    fn refresh_keeps_m_and_changes_shares() {
+        let (_, shares) = dealt(6);
+        let fresh = refresh_shares(PRIME, &shares, 3, &mut seeded(13)).unwrap();
+        assert_eq!(fresh.0, shares.0);
+        assert_ne!(fresh.1, shares.1);
+        assert_eq!(reconstruct(&fresh, PRIME).unwrap(), 42);
+    }
+
+    #[test]
+    fn refresh_rejects_malformed_shares() {
+        assert!(matches!(refresh_shares(PRIME, &(vec![1,2,3],vec![4,5]), 2, &mut seeded(1)),
+            Err(Error::LengthMismatch(3,2))));
+        assert!(matches!(refresh_shares(PRIME, &(vec![1,2],vec![4,97]), 1, &mut seeded(1)),
+            Err(Error::ShareValueOutOfRange(97))));
+    }
+
+    #[test]
+    fn scaled_shares_reconstruct_to_k_m() {
+        let (_, shares) = dealt(5);
+        for k in [0, 1, 5, 96, 200, -1, -5] {
+            let scaled = scale_shares(PRIME, &shares, k).unwrap();
+            let want = (PRIME + (k*42)%PRIME)%PRIME;
+            assert_eq!(reconstruct(&scaled, PRIME).unwrap(), want);
+        }
+    }
+
+    #[test]
+    fn mask_unmask_round_trip() {
+        let (_, shares) = dealt(5);
+        for pad in [0, 1, 50, 96, 150, -3] {
+            let masked = mask_shares(PRIME, &shares, pad).unwrap();
+            if pad % PRIME != 0 {
+                assert_ne!(masked.1, shares.1);
+            }
+            assert_eq!(unmask_shares(PRIME, &masked, pad).unwrap(), shares);
+        }
+    }
+
+    #[test]
+    fn added_shares_reconstruct_to_sum() {
+        let (_, a) = dealt(5);
+        // shares of B at the same x's as A
+        let pn_b = gen_poly(80, PRIME, 3, &mut seeded(17));
+        let b:Shares = (a.0.clone(), a.0.iter().map(|&x| eval_px_at_xi(PRIME, &pn_b, x)).collect());
+        let sum = add_shares(PRIME, &a, &b).unwrap();
+        assert_eq!(reconstruct(&sum, PRIME).unwrap(), (42 + 80) % PRIME);
+    }
+
+    #[test]
+    fn add_shares_rejects_mismatched_points() {
+        let a:Shares = (vec![1,2,3], vec![4,5,6]);
+        assert!(matches!(add_shares(PRIME, &a, &(vec![1,3,2], vec![4,5,6])), Err(Error::PointMismatch(2,3))));
+        assert!(matches!(add_shares(PRIME, &a, &(vec![1,2], vec![4,5])), Err(Error::LengthMismatch(3,2))));
+    }
+
+    #[test]
+    fn reconstruct_verbose_weights() {
+        let (_, shares) = dealt(4);
+        let (m, li) = reconstruct_verbose(&shares, PRIME).unwrap();
+        assert_eq!(m, 42);
+        assert_eq!(m, reconstruct(&shares, PRIME).unwrap());
+        assert_eq!(li.len(), 4);
+        // basis at 0 sums to 1, and sum yi*li is m
+        assert_eq!(li.iter().fold(0, |acc, &l| (acc + l) % PRIME), 1);
+        let by_hand = shares.1.iter().zip(li.iter()).fold(0, |acc, (&y, &l)| (acc + y*l) % PRIME);
+        assert_eq!(by_hand, 42);
+    }
+
+    #[test]
+    fn mask_unmask_reject_out_of_range_y() {
+        let bad:Shares = (vec![1,2,3], vec![200,5,6]);
+        assert!(matches!(mask_shares(97, &bad, 0), Err(Error::ShareValueOutOfRange(200))));
+        assert!(matches!(unmask_shares(97, &bad, 0), Err(Error::ShareValueOutOfRange(200))));
+        assert!(matches!(mask_shares(97, &(vec![1,2],vec![5]), 3), Err(Error::LengthMismatch(2,1))));
+    }
+
+    #[test]
+    fn scale_rejects_out_of_range_y() {
+        let bad:Shares = (vec![1,2,3], vec![200,5,6]);
+        assert!(matches!(scale_shares(97, &bad, 1), Err(Error::ShareValueOutOfRange(200))));
+    }
+
+    #[test]
+    fn issued_share_at_unreduced_x_verifies() {
+        let (pn, shares) = dealt(4);
+        for new_x in [-5, -96, 100, 2*PRIME + 7] {
+            if shares.0.contains(&((PRIME + new_x%PRIME)%PRIME)) { continue; }
+            let (x, y) = issue_share_for(&shares, PRIME, new_x).unwrap();
+            assert!((0..PRIME).contains(&x));
+            assert_eq!((x - new_x) % PRIME, 0);
+            assert!(verify_share(PRIME, &pn, x, y));
+        }
+    }
+
+    #[test]
+    fn primes_past_i32_products_are_rejected() {
+        // used to overflow and panic in interpolate_at
+        assert!(matches!(reconstruct(&(vec![1,2],vec![60000,60000]), 65537),
+            Err(Error::PrimeTooLarge(65537))));
+        let pn = gen_poly(6, 65537, 2, &mut seeded(1));
+        assert!(matches!(gen_share(65537, &pn, 3, &mut seeded(1)), Err(Error::PrimeTooLarge(65537))));
+    }
+
+    #[test]
+    fn largest_prime_below_max_works() {
+        // 46337 is the largest prime <= MAX_PRIME
+        let prime = 46337;
+        let (_, shares) = deal_seeded(12345, prime, 5, 8, [19;32]).unwrap();
+        assert_eq!(reconstruct(&shares, prime).unwrap(), 12345);
+        let big:Shares = (vec![prime-1, prime-2], vec![prime-1, prime-1]);
+        assert_eq!(reconstruct(&big, prime).unwrap(), prime-1);
+    }
+
+    #[test]
+    fn audit_flags_weak_patterns() {
+        let weak = |pn:&[i32]| matches!(audit_coefficients(17, pn), Err(Error::WeakCoefficients));
+        assert!(weak(&[]));
+        assert!(weak(&[6, 3, 0])); // zero leading coeff
+        assert!(weak(&[6, 0]));
+        assert!(weak(&[6, 5, 5, 5])); // all equal
+        assert!(weak(&[6, 15, 16, 0, 1])); // sequential, wraps mod 17
+    }
+
+    #[test]
+    fn audit_passes_short_and_real_polys() {
+        // < 3 non-constant coeffs aren't pattern checked
+        assert!(audit_coefficients(17, &[6]).is_ok());
+        assert!(audit_coefficients(17, &[6, 5, 5]).is_ok());
+        assert!(audit_coefficients(17, &[6, 4, 5]).is_ok());
+        for b in 0..16 {
+            let pn = gen_poly(6, 97, 7, &mut seeded(b));
+            assert!(audit_coefficients(97, &pn).is_ok());
+        }
+    }
+
+    #[test]
+    fn more_shares_than_field_points_is_an_error() {
+        let pn = gen_poly(6, 17, 3, &mut seeded(20));
+        assert!(matches!(gen_share(17, &pn, 17, &mut seeded(20)), Err(Error::SharesExceedFieldSize(17))));
+        assert_eq!(gen_share(17, &pn, 16, &mut seeded(20)).unwrap().0.len(), 16);
+    }
+
+    #[test]
+    fn same_share_twice_is_a_duplicate_point() {
+        assert!(matches!(reconstruct(&(vec![3,3],vec![1,1]),17), Err(Error::DuplicatePoint(3))));
+        assert!(matches!(reconstruct(&(vec![1,3,5,3],vec![1,2,3,2]),17), Err(Error::DuplicatePoint(3))));
+    }
+
+    #[test]
+    fn checkpoint_catches_tampered_shares() {
+        let (pn, shares) = dealt(5);
+        let cp = verification_point(PRIME, &pn, 50).unwrap();
+        assert!(verify_against_checkpoint(PRIME, &shares, &cp));
+        let mut bad = shares.clone();
+        bad.1[2] = (bad.1[2] + 1) % PRIME;
+        assert!(!verify_against_checkpoint(PRIME, &bad, &cp));
+        assert!(!verify_against_checkpoint(PRIME, &(vec![],vec![]), &cp));
+        assert!(matches!(verification_point(PRIME, &pn, 97), Err(Error::InvalidPoint(0))));
+    }
+}